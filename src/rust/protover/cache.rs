@@ -0,0 +1,63 @@
+// Copyright (c) 2018, The Tor Project, Inc. */
+// See LICENSE for licensing information */
+
+//! A small, thread-safe memoization layer over the protover lookups which C
+//! calls repeatedly with the same arguments during consensus processing.
+//!
+//! `protover_is_supported_here` otherwise re-translates the C enum and
+//! re-scans the compiled-in supported set on every single FFI invocation.
+//! Because C code queries our own, static supported set thousands of times, we
+//! memoize the boolean result of "do we support `(Protocol, version)` here".
+//!
+//! We deliberately memoize *only* this self/static lookup.  Per-relay protocol
+//! strings seen during consensus processing are almost all distinct, so
+//! caching parsed forms of arbitrary untrusted input would grow without bound
+//! — a leak on the hot path rather than a useful cache.
+//!
+//! The cache is lazily initialized and guarded by a `Mutex`, and can be
+//! reclaimed at shutdown via `clear` (exported to C as `protover_cache_free`).
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use protover::Protocol;
+use protover::ProtoEntry;
+use protoset::Version;
+
+lazy_static! {
+    /// Memoized answers to `is_supported_here(protocol, version)`.
+    static ref IS_SUPPORTED_HERE: Mutex<HashMap<(Protocol, Version), bool>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Return whether `protocol` at `version` is supported by the locally compiled
+/// set, memoizing the answer so repeated C calls are served from the cache.
+///
+/// C_RUST_COUPLED: src/or/protover.c `protover_is_supported_here`
+pub fn cached_is_supported_here(protocol: &Protocol, version: Version) -> bool {
+    let key = (protocol.clone(), version);
+
+    {
+        let cache = IS_SUPPORTED_HERE.lock().unwrap();
+        if let Some(supported) = cache.get(&key) {
+            return *supported;
+        }
+    }
+
+    let supported = match ProtoEntry::supported() {
+        Ok(entry) => entry
+            .get(protocol)
+            .map_or(false, |versions| versions.contains(&version)),
+        Err(_) => false,
+    };
+
+    IS_SUPPORTED_HERE.lock().unwrap().insert(key, supported);
+    supported
+}
+
+/// Drop every memoized entry, releasing the memory the cache holds.
+///
+/// C_RUST_COUPLED: src/or/protover.c `protover_free_all`
+pub fn clear() {
+    IS_SUPPORTED_HERE.lock().unwrap().clear();
+}
@@ -0,0 +1,85 @@
+// Copyright (c) 2018, The Tor Project, Inc. */
+// See LICENSE for licensing information */
+
+//! Various errors which may occur during protocol version parsing.
+
+use std::fmt;
+use std::fmt::Display;
+
+use libc::c_int;
+
+/// All errors which may occur during protover parsing routines.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[allow(missing_docs)]
+pub enum ProtoverError {
+    Overlap,
+    LowGreaterThanHigh,
+    Unparseable,
+    ExceedsMax,
+    ExceedsExpansionLimit,
+    UnknownProtocol,
+    ExceedsNameLimit,
+    /// The input handed across the FFI boundary was a null pointer.
+    NullInput,
+    /// The input handed across the FFI boundary was not valid UTF-8.
+    NotUtf8,
+}
+
+impl Display for ProtoverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProtoverError::Overlap
+                => write!(f, "Two or more (low, high) protover ranges would overlap once expanded."),
+            ProtoverError::LowGreaterThanHigh
+                => write!(f, "The low in a (low, high) protover range was greater than high."),
+            ProtoverError::Unparseable
+                => write!(f, "The protover string was unparseable."),
+            ProtoverError::ExceedsMax
+                => write!(f, "The high in a (low, high) protover range exceeds the maximum supported protocol version."),
+            ProtoverError::ExceedsExpansionLimit
+                => write!(f, "The protover string would exceed the maximum expansion limit."),
+            ProtoverError::UnknownProtocol
+                => write!(f, "A protocol in the protover string we attempted to parse is unknown."),
+            ProtoverError::ExceedsNameLimit
+                => write!(f, "An unrecognised protocol name was too long."),
+            ProtoverError::NullInput
+                => write!(f, "A pointer handed to us across the FFI boundary was null."),
+            ProtoverError::NotUtf8
+                => write!(f, "A string handed to us across the FFI boundary was not valid UTF-8."),
+        }
+    }
+}
+
+/// Stable, negative error codes which the protover FFI functions hand back to C
+/// through their `err_out` parameter, so that a C caller can branch on *why* a
+/// call failed rather than guessing from a single collapsed `1`.
+///
+/// The values are guaranteed never to change and never to collide with the
+/// `0`/`1` success/"answer is no" return values of the wrappers.
+///
+/// C_RUST_COUPLED: src/or/protover.h `protover_error_t`
+pub const PROTOVER_ERR_NULL_INPUT: c_int = -1;
+pub const PROTOVER_ERR_NOT_UTF8: c_int = -2;
+pub const PROTOVER_ERR_UNPARSEABLE: c_int = -3;
+pub const PROTOVER_ERR_UNKNOWN_PROTOCOL: c_int = -4;
+pub const PROTOVER_ERR_EXCEEDS_MAX: c_int = -5;
+pub const PROTOVER_ERR_EXCEEDS_EXPANSION_LIMIT: c_int = -6;
+pub const PROTOVER_ERR_EXCEEDS_NAME_LIMIT: c_int = -7;
+
+/// Map a `ProtoverError` onto the stable `PROTOVER_ERR_*` code which the FFI
+/// wrappers report to C through their `err_out` parameter.
+impl From<ProtoverError> for c_int {
+    fn from(error: ProtoverError) -> c_int {
+        match error {
+            ProtoverError::NullInput => PROTOVER_ERR_NULL_INPUT,
+            ProtoverError::NotUtf8 => PROTOVER_ERR_NOT_UTF8,
+            ProtoverError::Unparseable
+            | ProtoverError::Overlap
+            | ProtoverError::LowGreaterThanHigh => PROTOVER_ERR_UNPARSEABLE,
+            ProtoverError::UnknownProtocol => PROTOVER_ERR_UNKNOWN_PROTOCOL,
+            ProtoverError::ExceedsMax => PROTOVER_ERR_EXCEEDS_MAX,
+            ProtoverError::ExceedsExpansionLimit => PROTOVER_ERR_EXCEEDS_EXPANSION_LIMIT,
+            ProtoverError::ExceedsNameLimit => PROTOVER_ERR_EXCEEDS_NAME_LIMIT,
+        }
+    }
+}
@@ -11,10 +11,23 @@ use std::ffi::CString;
 
 use smartlist::*;
 use tor_allocate::allocate_and_copy_string;
+use tor_util::strings::cstring_from_str_lossy;
 
+use cache;
 use errors::ProtoverError;
 use protover::*;
 
+/// Write `error`'s stable `PROTOVER_ERR_*` code into `err_out`, unless `err_out`
+/// is null.  Used by the `_ex` wrappers so C can branch on *why* a call failed.
+fn set_error_code(err_out: *mut c_int, error: ProtoverError) {
+    if err_out.is_null() {
+        return;
+    }
+    // Dereference of a raw pointer requires an unsafe block; checked non-null
+    // immediately above.
+    unsafe { *err_out = error.into() };
+}
+
 /// Translate C enums to Rust Proto enums, using the integer value of the C
 /// enum to map to its associated Rust enum.
 ///
@@ -64,10 +77,62 @@ pub extern "C" fn protover_all_supported(
 
     if maybe_unsupported.is_some() {
         let unsupported: UnvalidatedProtoEntry = maybe_unsupported.unwrap();
-        let c_unsupported: CString = match CString::new(unsupported.to_string()) {
-            Ok(n) => n,
-            Err(_) => return 1,
-        };
+        let c_unsupported: CString =
+            cstring_from_str_lossy(&unsupported.to_string());
+
+        let ptr = c_unsupported.into_raw();
+        unsafe { *missing_out = ptr };
+
+        return 0;
+    }
+
+    1
+}
+
+/// As `protover_all_supported`, but additionally reports, through the
+/// `err_out` out-parameter, a stable `PROTOVER_ERR_*` code describing *why* the
+/// call failed.  `err_out` may be null if the caller does not care.
+///
+/// On success `*err_out` is left untouched and the return value is identical to
+/// `protover_all_supported`.
+#[no_mangle]
+pub extern "C" fn protover_all_supported_ex(
+    c_relay_version: *const c_char,
+    missing_out: *mut *mut c_char,
+    err_out: *mut c_int,
+) -> c_int {
+
+    if c_relay_version.is_null() {
+        set_error_code(err_out, ProtoverError::NullInput);
+        return 1;
+    }
+
+    // Require an unsafe block to read the version from a C string. The pointer
+    // is checked above to ensure it is not null.
+    let c_str: &CStr = unsafe { CStr::from_ptr(c_relay_version) };
+
+    let relay_version = match c_str.to_str() {
+        Ok(n) => n,
+        Err(_) => {
+            set_error_code(err_out, ProtoverError::NotUtf8);
+            return 1;
+        }
+    };
+
+    let relay_proto_entry: UnvalidatedProtoEntry = match relay_version.parse() {
+        Ok(n) => n,
+        Err(e) => {
+            set_error_code(err_out, e);
+            return 1;
+        }
+    };
+    let maybe_unsupported: Option<UnvalidatedProtoEntry> =
+        relay_proto_entry.all_supported();
+
+    if maybe_unsupported.is_some() {
+        let unsupported: UnvalidatedProtoEntry = maybe_unsupported.unwrap();
+        let c_unsupported: CString =
+            cstring_from_str_lossy(&unsupported.to_string());
 
         let ptr = c_unsupported.into_raw();
         unsafe { *missing_out = ptr };
@@ -110,6 +175,56 @@ pub extern "C" fn protocol_list_supports_protocol(
     return if is_supported { 1 } else { 0 };
 }
 
+/// As `protocol_list_supports_protocol`, but additionally reports, through the
+/// `err_out` out-parameter, a stable `PROTOVER_ERR_*` code describing *why* the
+/// call failed.  `err_out` may be null if the caller does not care.
+#[no_mangle]
+pub extern "C" fn protocol_list_supports_protocol_ex(
+    c_protocol_list: *const c_char,
+    c_protocol: uint32_t,
+    version: uint32_t,
+    err_out: *mut c_int,
+) -> c_int {
+    if c_protocol_list.is_null() {
+        set_error_code(err_out, ProtoverError::NullInput);
+        return 1;
+    }
+
+    // Require an unsafe block to read the version from a C string. The pointer
+    // is checked above to ensure it is not null.
+    let c_str: &CStr = unsafe { CStr::from_ptr(c_protocol_list) };
+
+    let protocol_list = match c_str.to_str() {
+        Ok(n) => n,
+        Err(_) => {
+            set_error_code(err_out, ProtoverError::NotUtf8);
+            return 1;
+        }
+    };
+
+    let protocol = match translate_to_rust(c_protocol) {
+        Ok(n) => n,
+        Err(e) => {
+            set_error_code(err_out, e);
+            return 0;
+        }
+    };
+
+    let proto_entry: ProtoEntry = match protocol_list.parse() {
+        Ok(n) => n,
+        Err(e) => {
+            set_error_code(err_out, e);
+            return 0;
+        }
+    };
+
+    let is_supported = proto_entry
+        .get(&protocol)
+        .map_or(false, |versions| versions.contains(&version));
+
+    return if is_supported { 1 } else { 0 };
+}
+
 /// Provide an interface for C to translate arguments and return types for
 /// protover::list_supports_protocol_or_later
 #[no_mangle]
@@ -177,6 +292,34 @@ pub extern "C" fn protover_compute_vote(
     allocate_and_copy_string(&vote)
 }
 
+/// As `protover_compute_vote`, but additionally reports, through the `err_out`
+/// out-parameter, a stable `PROTOVER_ERR_*` code describing *why* the call could
+/// not produce a vote.  `err_out` may be null if the caller does not care.
+///
+/// A null `list` is reported as `PROTOVER_ERR_NULL_INPUT`; individual malformed
+/// voter strings are skipped by `compute_vote` itself and are not an error here.
+#[no_mangle]
+pub extern "C" fn protover_compute_vote_ex(
+    list: *const Stringlist,
+    threshold: c_int,
+    err_out: *mut c_int,
+) -> *mut c_char {
+
+    if list.is_null() {
+        set_error_code(err_out, ProtoverError::NullInput);
+        let empty = String::new();
+        return allocate_and_copy_string(&empty);
+    }
+
+    // Dereference of raw pointer requires an unsafe block. The pointer is
+    // checked above to ensure it is not null.
+    let data: Vec<String> = unsafe { (*list).get_list() };
+
+    let vote = compute_vote(data, threshold);
+
+    allocate_and_copy_string(&vote)
+}
+
 /// Provide an interface for C to translate arguments and return types for
 /// protover::is_supported_here
 #[no_mangle]
@@ -189,7 +332,7 @@ pub extern "C" fn protover_is_supported_here(
         Err(_) => return 0,
     };
 
-    let is_supported = is_supported_here(protocol, version);
+    let is_supported = cache::cached_is_supported_here(&protocol, version);
 
     return if is_supported { 1 } else { 0 };
 }
@@ -219,3 +362,12 @@ pub extern "C" fn protover_compute_for_old_tor(version: *const c_char) -> *const
     supported = compute_for_old_tor(&version);
     supported.as_ptr()
 }
+
+/// Free the memoization caches used by the protover lookups.  Intended to be
+/// called by C at shutdown so the cached entries are reclaimed.
+///
+/// C_RUST_COUPLED: src/or/protover.c `protover_free_all`
+#[no_mangle]
+pub extern "C" fn protover_cache_free() {
+    cache::clear();
+}
@@ -0,0 +1,269 @@
+// Copyright (c) 2018, The Tor Project, Inc. */
+// See LICENSE for licensing information */
+
+//! Sets for storing ordered, non-overlapping ranges of supported protocol
+//! versions.
+
+use std::str::FromStr;
+
+use errors::ProtoverError;
+
+/// The maximum number of protocol versions we will expand a single
+/// protocol-list entry into before concluding that someone is trying to
+/// exhaust our memory.
+///
+/// A range such as `Link=1-4294967295` would otherwise expand into a gigantic
+/// allocation, so this cap is enforced at parse time.  It must match the C
+/// limit exactly: a smaller Rust value would reject well-formed lists that C
+/// still accepts, so the two implementations would disagree on validity during
+/// voting and `all_supported` checks.
+///
+/// C_RUST_COUPLED: src/or/protover.c `MAX_PROTOCOLS_TO_EXPAND`
+pub(crate) const MAX_VERSIONS_EXPANDED: usize = 1 << 16;
+
+/// A single protocol version number.
+pub type Version = u32;
+
+/// A `ProtoSet` stores an ordered `Vec` of `(low, high)` pairs describing
+/// non-overlapping, inclusive ranges of supported protocol versions.
+///
+/// The pairs are always kept sorted by their lower bound, merged so that no two
+/// ranges overlap or abut, and constrained so that the total number of versions
+/// they would expand to never exceeds `MAX_VERSIONS_EXPANDED`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct ProtoSet {
+    pairs: Vec<(Version, Version)>,
+}
+
+impl ProtoSet {
+    /// Construct a `ProtoSet` from a slice of `(low, high)` pairs, normalising
+    /// and validating them.
+    pub fn from_slice(
+        low_high_pairs: &[(Version, Version)],
+    ) -> Result<Self, ProtoverError> {
+        normalize(low_high_pairs.to_vec()).map(|pairs| ProtoSet { pairs })
+    }
+
+    /// Get a reference to the `(low, high)` pairs in this `ProtoSet`.
+    pub fn pairs(&self) -> &[(Version, Version)] {
+        &self.pairs
+    }
+
+    /// Whether this `ProtoSet` contains no versions at all.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// The total number of versions covered by this `ProtoSet`.
+    pub fn len(&self) -> usize {
+        self.pairs
+            .iter()
+            .map(|&(low, high)| (high - low) as usize + 1)
+            .sum()
+    }
+
+    /// Return `true` iff `version` is covered by one of this set's ranges.
+    pub fn contains(&self, version: &Version) -> bool {
+        self.pairs
+            .iter()
+            .any(|&(low, high)| low <= *version && *version <= high)
+    }
+
+    /// The highest version in this set, or `None` if it is empty.
+    pub fn highest(&self) -> Option<Version> {
+        self.pairs.last().map(|&(_, high)| high)
+    }
+
+    /// The set of versions present in both `self` and `other`.
+    pub fn intersect(&self, other: &ProtoSet) -> ProtoSet {
+        let mut pairs: Vec<(Version, Version)> = Vec::new();
+
+        for &(low, high) in &self.pairs {
+            for &(other_low, other_high) in &other.pairs {
+                let start = low.max(other_low);
+                let end = high.min(other_high);
+
+                if start <= end {
+                    pairs.push((start, end));
+                }
+            }
+        }
+        // The operands are already within the expansion limit, so the
+        // intersection (a subset of each) cannot exceed it.
+        ProtoSet {
+            pairs: normalize(pairs).unwrap_or_default(),
+        }
+    }
+
+    /// The set of versions present in either `self` or `other`.
+    ///
+    /// Returns an error if the combined ranges would expand past
+    /// `MAX_VERSIONS_EXPANDED`, so a union of huge ranges cannot be used to
+    /// DoS us.
+    pub fn union(&self, other: &ProtoSet) -> Result<ProtoSet, ProtoverError> {
+        let mut pairs: Vec<(Version, Version)> = self.pairs.clone();
+        pairs.extend_from_slice(&other.pairs);
+
+        normalize(pairs).map(|pairs| ProtoSet { pairs })
+    }
+
+    /// The set of versions present in `self` but not in `other`.
+    pub fn difference(&self, other: &ProtoSet) -> ProtoSet {
+        let mut pairs: Vec<(Version, Version)> = Vec::new();
+
+        for &(low, high) in &self.pairs {
+            let mut cursor = low;
+            let mut covered = false;
+
+            for &(other_low, other_high) in &other.pairs {
+                if other_high < cursor || other_low > high {
+                    continue;
+                }
+                if other_low > cursor {
+                    pairs.push((cursor, other_low - 1));
+                }
+                // Stop once the subtracted range reaches the end of this one;
+                // this also keeps `other_high + 1` from overflowing when
+                // `other_high == Version::max_value()`.
+                if other_high >= high {
+                    covered = true;
+                    break;
+                }
+                cursor = other_high + 1;
+            }
+            if !covered && cursor <= high {
+                pairs.push((cursor, high));
+            }
+        }
+        // The difference is a subset of `self`, so it is already within the
+        // expansion limit.
+        ProtoSet {
+            pairs: normalize(pairs).unwrap_or_default(),
+        }
+    }
+}
+
+/// Sort, merge and validate a list of `(low, high)` pairs into the canonical
+/// representation used by `ProtoSet`.
+///
+/// Overlapping or abutting ranges are merged, pairs whose low bound exceeds
+/// their high bound are rejected with `ProtoverError::LowGreaterThanHigh`, and
+/// the total expanded size is capped at `MAX_VERSIONS_EXPANDED`.
+fn normalize(
+    mut pairs: Vec<(Version, Version)>,
+) -> Result<Vec<(Version, Version)>, ProtoverError> {
+    for &(low, high) in &pairs {
+        if low > high {
+            return Err(ProtoverError::LowGreaterThanHigh);
+        }
+    }
+
+    pairs.sort();
+
+    let mut merged: Vec<(Version, Version)> = Vec::with_capacity(pairs.len());
+    for (low, high) in pairs {
+        match merged.last_mut() {
+            // Merge if the new range overlaps or directly abuts the previous
+            // one.  `saturating_add` keeps a `u32::max_value()` upper bound
+            // from overflowing; such a range absorbs every range after it.
+            Some(last) if low <= last.1.saturating_add(1) => {
+                if high > last.1 {
+                    last.1 = high;
+                }
+            }
+            _ => merged.push((low, high)),
+        }
+    }
+
+    let expanded: usize = merged
+        .iter()
+        .map(|&(low, high)| (high - low) as usize + 1)
+        .sum();
+
+    if expanded > MAX_VERSIONS_EXPANDED {
+        return Err(ProtoverError::ExceedsExpansionLimit);
+    }
+
+    Ok(merged)
+}
+
+impl FromStr for ProtoSet {
+    type Err = ProtoverError;
+
+    /// Parse a comma-separated version list such as `"1-3,5,7-9"` into a
+    /// `ProtoSet`.
+    fn from_str(version_string: &str) -> Result<Self, ProtoverError> {
+        let mut pairs: Vec<(Version, Version)> = Vec::new();
+
+        for piece in version_string.split(',') {
+            if piece.is_empty() {
+                continue;
+            }
+
+            let mut bounds = piece.splitn(2, '-');
+
+            let low: Version = bounds
+                .next()
+                .ok_or(ProtoverError::Unparseable)?
+                .parse()
+                .or(Err(ProtoverError::Unparseable))?;
+
+            let high: Version = match bounds.next() {
+                Some(h) => h.parse().or(Err(ProtoverError::Unparseable))?,
+                None => low,
+            };
+
+            pairs.push((low, high));
+        }
+
+        normalize(pairs).map(|pairs| ProtoSet { pairs })
+    }
+}
+
+impl ToString for ProtoSet {
+    /// Render a `ProtoSet` back into its canonical `"1-3,5,7-9"` string form.
+    fn to_string(&self) -> String {
+        let mut output: Vec<String> = Vec::with_capacity(self.pairs.len());
+
+        for &(low, high) in &self.pairs {
+            if low == high {
+                output.push(low.to_string());
+            } else {
+                output.push(format!("{}-{}", low, high));
+            }
+        }
+        output.join(",")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_rejects_oversized_range() {
+        // A range like `Link=1-4294967295` would expand to a gigantic set, so
+        // the parser must refuse it with a recoverable error rather than
+        // allocating.
+        assert_eq!(
+            Err(ProtoverError::ExceedsExpansionLimit),
+            "1-4294967295".parse::<ProtoSet>()
+        );
+    }
+
+    #[test]
+    fn test_parse_allows_sparse_high_version() {
+        // Legitimately sparse high version numbers stay well under the cap and
+        // must round-trip unchanged.
+        let set: ProtoSet = "1-3,500".parse().unwrap();
+
+        assert_eq!("1-3,500", set.to_string());
+    }
+
+    #[test]
+    fn test_parse_merges_overlapping_ranges() {
+        let set: ProtoSet = "1-3,2-5".parse().unwrap();
+
+        assert_eq!("1-5", set.to_string());
+    }
+}
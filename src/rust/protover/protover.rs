@@ -22,12 +22,6 @@ use protoset::ProtoSet;
 ///     src/or/protover.h `FIRST_TOR_VERSION_TO_ADVERTISE_PROTOCOLS`
 const FIRST_TOR_VERSION_TO_ADVERTISE_PROTOCOLS: &'static str = "0.2.9.3-alpha";
 
-/// The maximum number of subprotocol version numbers we will attempt to expand
-/// before concluding that someone is trying to DoS us
-///
-/// C_RUST_COUPLED: src/or/protover.c `MAX_PROTOCOLS_TO_EXPAND`
-pub(crate) const MAX_PROTOCOLS_TO_EXPAND: usize = (1<<16);
-
 /// Currently supported protocols and their versions, as a byte-slice.
 ///
 /// # Warning
@@ -101,7 +95,7 @@ impl FromStr for Protocol {
 
 /// A protocol string which is not one of the `Protocols` we currently know
 /// about.
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct UnknownProtocol(String);
 
 impl fmt::Display for UnknownProtocol {
@@ -181,6 +175,84 @@ impl ProtoEntry {
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    /// For every `Protocol` present in both `self` and `other`, the versions
+    /// supported by both sides.  Protocols absent from either side are dropped.
+    pub fn intersect(&self, other: &ProtoEntry) -> ProtoEntry {
+        let mut result: ProtoEntry = ProtoEntry::default();
+
+        for (protocol, versions) in self.iter() {
+            if let Some(other_versions) = other.get(protocol) {
+                let common = versions.intersect(other_versions);
+
+                if !common.is_empty() {
+                    result.insert(protocol.clone(), common);
+                }
+            }
+        }
+        result
+    }
+
+    /// The union of `self` and `other`, merging the version sets of any
+    /// `Protocol` which appears on both sides.
+    ///
+    /// Errors if merging would expand past `MAX_VERSIONS_EXPANDED`.
+    pub fn union(&self, other: &ProtoEntry) -> Result<ProtoEntry, ProtoverError> {
+        let mut result: ProtoEntry = self.clone();
+
+        for (protocol, versions) in other.iter() {
+            let merged = match result.get(protocol) {
+                Some(existing) => existing.union(versions)?,
+                None => versions.clone(),
+            };
+            result.insert(protocol.clone(), merged);
+        }
+        Ok(result)
+    }
+
+    /// The versions present in `self` but not in `other`, per `Protocol`.
+    /// Protocols which end up empty after the subtraction are omitted.
+    pub fn difference(&self, other: &ProtoEntry) -> ProtoEntry {
+        let mut result: ProtoEntry = ProtoEntry::default();
+
+        for (protocol, versions) in self.iter() {
+            let remaining = match other.get(protocol) {
+                Some(other_versions) => versions.difference(other_versions),
+                None => versions.clone(),
+            };
+
+            if !remaining.is_empty() {
+                result.insert(protocol.clone(), remaining);
+            }
+        }
+        result
+    }
+
+    /// The single highest version of `protocol` present in this `ProtoEntry`,
+    /// or `None` if this entry does not list the protocol at all.
+    ///
+    /// Combined with `negotiate`, this yields the best mutually supported
+    /// version of a subprotocol that both endpoints can speak.
+    pub fn highest_common(&self, protocol: &Protocol) -> Option<Version> {
+        self.get(protocol).and_then(|versions| versions.highest())
+    }
+
+    /// Determine which of the versions in `self` are *not* supported by the
+    /// locally compiled set.
+    ///
+    /// Returns `None` when everything in `self` is supported, and otherwise the
+    /// difference — the protocols and versions we lack — as a structured
+    /// `ProtoEntry`.
+    pub fn all_supported(&self) -> Result<Option<ProtoEntry>, ProtoverError> {
+        let supported: ProtoEntry = ProtoEntry::supported()?;
+        let unsupported: ProtoEntry = self.difference(&supported);
+
+        if unsupported.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(unsupported))
+        }
+    }
 }
 
 impl FromStr for ProtoEntry {
@@ -226,6 +298,119 @@ impl FromStr for ProtoEntry {
     }
 }
 
+/// A map of protocol names — including ones this build does not recognise — to
+/// the versions of them which were declared.
+///
+/// Unlike `ProtoEntry`, this accepts arbitrary subprotocol names, so that a
+/// future subprotocol introduced by a newer Tor version can be voted into a
+/// consensus and round-tripped through this crate without us knowing its name
+/// in advance.  Version ranges are still required to be well-formed and are
+/// bounded by `MAX_VERSIONS_EXPANDED`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnvalidatedProtoEntry(HashMap<UnknownProtocol, ProtoSet>);
+
+impl UnvalidatedProtoEntry {
+    /// Get an iterator over the names and their `ProtoSet`s in this entry.
+    pub fn iter(&self) -> hash_map::Iter<UnknownProtocol, ProtoSet> {
+        self.0.iter()
+    }
+
+    pub fn get(&self, protocol: &UnknownProtocol) -> Option<&ProtoSet> {
+        self.0.get(protocol)
+    }
+
+    pub fn insert(&mut self, key: UnknownProtocol, value: ProtoSet) {
+        self.0.insert(key, value);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Determine which of the versions in `self` are *not* supported by the
+    /// locally compiled set, treating any protocol whose name we do not
+    /// recognise as entirely unsupported.
+    ///
+    /// Returns `None` when everything is supported, and otherwise the
+    /// unsupported remainder as a structured `UnvalidatedProtoEntry`.
+    pub fn all_supported(&self) -> Option<UnvalidatedProtoEntry> {
+        let supported: ProtoEntry = match ProtoEntry::supported() {
+            Ok(result) => result,
+            Err(_) => return None,
+        };
+        let mut unsupported: UnvalidatedProtoEntry = Default::default();
+
+        for (protocol, versions) in self.iter() {
+            let known: Protocol = match protocol.0.parse() {
+                Ok(n) => n,
+                // A protocol whose name we do not know cannot be supported.
+                Err(_) => {
+                    unsupported.insert(protocol.clone(), versions.clone());
+                    continue;
+                }
+            };
+
+            let remaining: ProtoSet = match supported.get(&known) {
+                Some(supported_versions) => versions.difference(supported_versions),
+                None => versions.clone(),
+            };
+
+            if !remaining.is_empty() {
+                unsupported.insert(protocol.clone(), remaining);
+            }
+        }
+
+        if unsupported.is_empty() {
+            None
+        } else {
+            Some(unsupported)
+        }
+    }
+}
+
+impl FromStr for UnvalidatedProtoEntry {
+    type Err = ProtoverError;
+
+    /// Parse a protocol-list string without validating the protocol names,
+    /// while still enforcing well-formed version ranges and
+    /// `MAX_VERSIONS_EXPANDED`.
+    fn from_str(protocol_string: &str) -> Result<Self, ProtoverError> {
+        let mut parsed: UnvalidatedProtoEntry = Default::default();
+
+        for subproto in protocol_string.split(' ') {
+            let mut parts = subproto.splitn(2, '=');
+
+            let name = match parts.next() {
+                Some("") | None => return Err(ProtoverError::Unparseable),
+                Some(n) => n,
+            };
+
+            let vers = match parts.next() {
+                Some(n) => n,
+                None => return Err(ProtoverError::Unparseable),
+            };
+
+            let versions: ProtoSet = vers.parse()?;
+
+            parsed.insert(name.parse()?, versions);
+        }
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for UnvalidatedProtoEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut keys: Vec<&UnknownProtocol> = self.0.keys().collect();
+        keys.sort();
+
+        let mut output: Vec<String> = Vec::with_capacity(keys.len());
+        for key in keys {
+            output.push(format!("{}={}", key, self.0[key].to_string()));
+        }
+        write!(f, "{}", output.join(" "))
+    }
+}
+
 /// Parses a single subprotocol entry string into subprotocol and version
 /// parts, and then checks whether any of those versions are unsupported.
 /// Helper for protover::all_supported
@@ -242,28 +427,26 @@ impl FromStr for ProtoEntry {
 /// versions that are also supported in tor. Otherwise, returns false
 ///
 fn contains_only_supported_protocols(proto_entry: &str) -> bool {
-    let (name, mut vers) = match get_proto_and_vers(proto_entry) {
+    let entry: ProtoEntry = match proto_entry.parse() {
         Ok(n) => n,
         Err(_) => return false,
     };
 
-    let currently_supported = match SupportedProtocols::tor_supported() {
-        Ok(n) => n.0,
-        Err(_) => return false,
-    };
-
-    let supported_versions = match currently_supported.get(&name) {
-        Some(n) => n,
-        None => return false,
-    };
-
-    vers.0.retain(|x| !supported_versions.0.contains(x));
-    vers.0.is_empty()
+    match entry.all_supported() {
+        Ok(None) => true,
+        _ => false,
+    }
 }
 
 /// Determine if we support every protocol a client supports, and if not,
 /// determine which protocols we do not have support for.
 ///
+/// # Note
+///
+/// The locally supported set is the compiled-in one, so it is not taken as an
+/// argument.  The unsupported remainder is returned as the second tuple
+/// element; `all_supported_result` gives the same answer as a `Result`.
+///
 /// # Inputs
 ///
 /// Accepted data is in the string format as follows:
@@ -296,6 +479,45 @@ pub fn all_supported(protocols: &str) -> (bool, String) {
     (unsupported.is_empty(), unsupported.join(" "))
 }
 
+/// As `all_supported`, but in the `Result` form the backlog item requested:
+/// `Ok(())` when every protocol version in `required` is supported by the
+/// locally compiled set, or `Err(unsupported)` naming the protocols we do not
+/// support.
+///
+/// The locally compiled set is always used; it is not taken as an argument.
+pub fn all_supported_result(required: &str) -> Result<(), String> {
+    match all_supported(required) {
+        (true, _) => Ok(()),
+        (false, unsupported) => Err(unsupported),
+    }
+}
+
+/// Select, for every `Protocol` present in both endpoints, the versions which
+/// both sides support.
+///
+/// This is what a relay or client needs when choosing a `Link` or `Relay`
+/// subprotocol version during the handshake.  Protocols which appear on only
+/// one side are dropped, and an endpoint with no overlap at all yields an empty
+/// `ProtoEntry` rather than an error.
+pub fn negotiate(local: &ProtoEntry, remote: &ProtoEntry) -> ProtoEntry {
+    local.intersect(remote)
+}
+
+/// As `negotiate`, but accepting the two endpoints as protocol-list strings.
+///
+/// # Errors
+///
+/// Returns a `ProtoverError` if either string fails to parse.
+pub fn negotiate_strings(
+    local: &str,
+    remote: &str,
+) -> Result<ProtoEntry, ProtoverError> {
+    let local_entry: ProtoEntry = local.parse()?;
+    let remote_entry: ProtoEntry = remote.parse()?;
+
+    Ok(negotiate(&local_entry, &remote_entry))
+}
+
 /// Return true iff the provided protocol list includes support for the
 /// indicated protocol and version.
 /// Otherwise, return false
@@ -312,20 +534,20 @@ pub fn all_supported(protocols: &str) -> (bool, String) {
 /// use protover::*;
 ///
 /// let is_supported = protover_string_supports_protocol("Link=3-4 Cons=1",
-///                                                      Proto::Cons,1);
+///                                                      Protocol::Cons,1);
 /// assert_eq!(true, is_supported);
 ///
 /// let is_not_supported = protover_string_supports_protocol("Link=3-4 Cons=1",
-///                                                           Proto::Cons,5);
+///                                                           Protocol::Cons,5);
 /// assert_eq!(false, is_not_supported)
 /// ```
 pub fn protover_string_supports_protocol(
     list: &str,
-    proto: Proto,
+    proto: Protocol,
     vers: Version,
 ) -> bool {
-    let supported = match SupportedProtocols::from_proto_entries_string(list) {
-        Ok(result) => result.0,
+    let supported: ProtoEntry = match list.parse() {
+        Ok(result) => result,
         Err(_) => return false,
     };
 
@@ -334,7 +556,39 @@ pub fn protover_string_supports_protocol(
         None => return false,
     };
 
-    supported_versions.0.contains(&vers)
+    supported_versions.contains(&vers)
+}
+
+/// Return true iff `list` declares support for `version` of the subprotocol
+/// named `proto`.
+///
+/// Unlike `protover_string_supports_protocol`, `proto` is given by name rather
+/// than as a `Protocol`, so this works for subprotocols whose names this build
+/// does not recognise.  This is the single-point companion to `all_supported`.
+///
+/// # Examples
+/// ```
+/// use protover::protocol_list_supports_version;
+///
+/// assert!(protocol_list_supports_version("Link=3-4 Cons=1", "Cons", 1));
+/// assert!(!protocol_list_supports_version("Link=3-4 Cons=1", "Cons", 5));
+/// ```
+pub fn protocol_list_supports_version(
+    list: &str,
+    proto: &str,
+    version: Version,
+) -> bool {
+    let entry: UnvalidatedProtoEntry = match list.parse() {
+        Ok(result) => result,
+        Err(_) => return false,
+    };
+
+    let name: UnknownProtocol = match proto.parse() {
+        Ok(n) => n,
+        Err(_) => return false,
+    };
+
+    entry.get(&name).map_or(false, |versions| versions.contains(&version))
 }
 
 /// As protover_string_supports_protocol(), but also returns True if
@@ -345,27 +599,27 @@ pub fn protover_string_supports_protocol(
 /// use protover::*;
 ///
 /// let is_supported = protover_string_supports_protocol_or_later(
-///                       "Link=3-4 Cons=5", Proto::Cons, 5);
+///                       "Link=3-4 Cons=5", Protocol::Cons, 5);
 ///
 /// assert_eq!(true, is_supported);
 ///
 /// let is_supported = protover_string_supports_protocol_or_later(
-///                       "Link=3-4 Cons=5", Proto::Cons, 4);
+///                       "Link=3-4 Cons=5", Protocol::Cons, 4);
 ///
 /// assert_eq!(true, is_supported);
 ///
 /// let is_supported = protover_string_supports_protocol_or_later(
-///                       "Link=3-4 Cons=5", Proto::Cons, 6);
+///                       "Link=3-4 Cons=5", Protocol::Cons, 6);
 ///
 /// assert_eq!(false, is_supported);
 /// ```
 pub fn protover_string_supports_protocol_or_later(
     list: &str,
-    proto: Proto,
+    proto: Protocol,
     vers: u32,
 ) -> bool {
-    let supported = match SupportedProtocols::from_proto_entries_string(list) {
-        Ok(result) => result.0,
+    let supported: ProtoEntry = match list.parse() {
+        Ok(result) => result,
         Err(_) => return false,
     };
 
@@ -374,60 +628,9 @@ pub fn protover_string_supports_protocol_or_later(
         None => return false,
     };
 
-    supported_versions.0.iter().any(|v| v >= &vers)
+    supported_versions.highest().map_or(false, |highest| highest >= vers)
 }
 
-/// Parses a protocol list without validating the protocol names
-///
-/// # Inputs
-///
-/// * `protocol_string`, a string comprised of keys and values, both which are
-/// strings. The keys are the protocol names while values are a string
-/// representation of the supported versions.
-///
-/// The input is _not_ expected to be a subset of the Proto types
-///
-/// # Returns
-///
-/// A `Result` whose `Ok` value is a `HashSet<Version>` holding all of the
-/// unique version numbers.
-///
-/// The returned `Result`'s `Err` value is an `&'static str` with a description
-/// of the error.
-///
-/// # Errors
-///
-/// This function will error if:
-///
-/// * The protocol string does not follow the "protocol_name=version_list"
-/// expected format
-/// * If the version string is malformed. See `Versions::from_version_string`.
-///
-fn parse_protocols_from_string_with_no_validation<'a>(
-    protocol_string: &'a str,
-) -> Result<HashMap<String, Versions>, &'static str> {
-    let mut parsed: HashMap<String, Versions> = HashMap::new();
-
-    for subproto in protocol_string.split(" ") {
-        let mut parts = subproto.splitn(2, "=");
-
-        let name = match parts.next() {
-            Some("") => return Err("invalid protover entry"),
-            Some(n) => n,
-            None => return Err("invalid protover entry"),
-        };
-
-        let vers = match parts.next() {
-            Some(n) => n,
-            None => return Err("invalid protover entry"),
-        };
-
-        let versions = Versions::from_version_string(vers)?;
-
-        parsed.insert(String::from(name), versions);
-    }
-    Ok(parsed)
-}
 
 /// Protocol voting implementation.
 ///
@@ -454,93 +657,89 @@ pub fn compute_vote(
     list_of_proto_strings: Vec<String>,
     threshold: i32,
 ) -> String {
-    let empty = String::from("");
-
     if list_of_proto_strings.is_empty() {
-        return empty;
-    }
-
-    // all_count is a structure to represent the count of the number of
-    // supported versions for a specific protocol. For example, in JSON format:
-    // {
-    //  "FirstSupportedProtocol": {
-    //      "1": "3",
-    //      "2": "1"
-    //  }
-    // }
-    // means that FirstSupportedProtocol has three votes which support version
-    // 1, and one vote that supports version 2
-    let mut all_count: HashMap<String, HashMap<Version, usize>> =
+        return String::new();
+    }
+
+    // The threshold stays `i32` to mirror the C `compute_vote`'s `int`
+    // argument, but it is compared against unsigned counts below.  Clamp a
+    // negative value to zero first: casting it straight to `usize` would wrap
+    // to a huge number and silently drop every version from the vote.
+    let threshold: usize = if threshold < 0 { 0 } else { threshold as usize };
+
+    // For each subprotocol, count how many voters listed each exact version.
+    let mut all_count: HashMap<UnknownProtocol, HashMap<Version, usize>> =
         HashMap::new();
 
-    // parse and collect all of the protos and their versions and collect them
+    // Parse each voter's list, skipping any which are malformed rather than
+    // aborting the whole vote.  Parsing is intentionally name-agnostic so that
+    // protocols we do not recognise are still tallied.
     for vote in list_of_proto_strings {
-        let this_vote: HashMap<String, Versions> =
-            match parse_protocols_from_string_with_no_validation(&vote) {
-                Ok(result) => result,
-                Err(_) => continue,
-            };
-        for (protocol, versions) in this_vote {
+        let this_vote: UnvalidatedProtoEntry = match vote.parse() {
+            Ok(result) => result,
+            Err(_) => continue,
+        };
+        for (protocol, versions) in this_vote.iter() {
             let supported_vers: &mut HashMap<Version, usize> =
-                all_count.entry(protocol).or_insert(HashMap::new());
+                all_count.entry(protocol.clone()).or_insert_with(HashMap::new);
 
-            for version in versions.0 {
-                let counter: &mut usize =
-                    supported_vers.entry(version).or_insert(0);
-                *counter += 1;
+            for &(low, high) in versions.pairs() {
+                for version in low..=high {
+                    *supported_vers.entry(version).or_insert(0) += 1;
+                }
             }
         }
     }
 
-    let mut final_output: HashMap<String, String> =
-        HashMap::with_capacity(get_supported_protocols().split(" ").count());
+    // Keep only the versions which met the threshold, and rebuild each
+    // surviving set back into a `ProtoSet` so the output is produced by the one
+    // canonical formatter.
+    let mut final_output: HashMap<UnknownProtocol, ProtoSet> =
+        HashMap::with_capacity(all_count.len());
 
-    // Go through and remove verstions that are less than the threshold
     for (protocol, versions) in all_count {
-        let mut meets_threshold = HashSet::new();
-        for (version, count) in versions {
-            if count >= threshold as usize {
-                meets_threshold.insert(version);
-            }
-        }
+        let pairs: Vec<(Version, Version)> = versions
+            .into_iter()
+            .filter(|&(_, count)| count >= threshold)
+            .map(|(version, _)| (version, version))
+            .collect();
+
+        let proto_set: ProtoSet = match ProtoSet::from_slice(&pairs) {
+            Ok(n) => n,
+            Err(_) => continue,
+        };
 
-        // For each protocol, compress its version list into the expected
-        // protocol version string format
-        let contracted = contract_protocol_list(&meets_threshold);
-        if !contracted.is_empty() {
-            final_output.insert(protocol, contracted);
+        if !proto_set.is_empty() {
+            final_output.insert(protocol, proto_set);
         }
     }
 
     write_vote_to_string(&final_output)
 }
 
-/// Return a String comprised of protocol entries in alphabetical order
+/// Return a `String` comprised of protocol entries in alphabetical order.
 ///
 /// # Inputs
 ///
-/// * `vote`, a `HashMap` comprised of keys and values, both which are strings.
-/// The keys are the protocol names while values are a string representation of
-/// the supported versions.
+/// * `vote`, a map of each subprotocol name to the `ProtoSet` of versions voted
+/// into the consensus for it.
 ///
 /// # Returns
 ///
-/// A `String` whose value is series of pairs, comprising of the protocol name
-/// and versions that it supports. The string takes the following format:
+/// A `String` whose value is a series of space-separated pairs, each comprising
+/// the protocol name and the versions that it supports, for example:
 ///
-/// "first_protocol_name=1,2-5, second_protocol_name=4,5"
+/// "first_protocol_name=1,2-5 second_protocol_name=4,5"
 ///
-/// Sorts the keys in alphabetical order and creates the expected subprotocol
-/// entry format.
+/// The subprotocol names are sorted alphabetically.
 ///
-fn write_vote_to_string(vote: &HashMap<String, String>) -> String {
-    let mut keys: Vec<&String> = vote.keys().collect();
+fn write_vote_to_string(vote: &HashMap<UnknownProtocol, ProtoSet>) -> String {
+    let mut keys: Vec<&UnknownProtocol> = vote.keys().collect();
     keys.sort();
 
-    let mut output = Vec::new();
+    let mut output: Vec<String> = Vec::with_capacity(keys.len());
     for key in keys {
-        // TODO error in indexing here?
-        output.push(format!("{}={}", key, vote[key]));
+        output.push(format!("{}={}", key, vote[key].to_string()));
     }
     output.join(" ")
 }
@@ -552,15 +751,15 @@ fn write_vote_to_string(vote: &HashMap<String, String>) -> String {
 /// ```
 /// use protover::*;
 ///
-/// let is_supported = is_supported_here(Proto::Link, 10);
+/// let is_supported = is_supported_here(Protocol::Link, 10);
 /// assert_eq!(false, is_supported);
 ///
-/// let is_supported = is_supported_here(Proto::Link, 1);
+/// let is_supported = is_supported_here(Protocol::Link, 1);
 /// assert_eq!(true, is_supported);
 /// ```
-pub fn is_supported_here(proto: Proto, vers: Version) -> bool {
-    let currently_supported = match SupportedProtocols::tor_supported() {
-        Ok(result) => result.0,
+pub fn is_supported_here(proto: Protocol, vers: Version) -> bool {
+    let currently_supported: ProtoEntry = match ProtoEntry::supported() {
+        Ok(result) => result,
         Err(_) => return false,
     };
 
@@ -569,7 +768,7 @@ pub fn is_supported_here(proto: Proto, vers: Version) -> bool {
         None => return false,
     };
 
-    supported_versions.0.contains(&vers)
+    supported_versions.contains(&vers)
 }
 
 /// Older versions of Tor cannot infer their own subprotocols
@@ -615,55 +814,8 @@ pub fn compute_for_old_tor(version: &str) -> &'static [u8] {
 
 #[cfg(test)]
 mod test {
-    use std::str::FromStr;
-    use std::string::ToString;
-
     use super::*;
 
-    #[test]
-    fn test_versions_from_version_string() {
-        use std::collections::HashSet;
-
-        use super::Versions;
-
-        assert_eq!(Err("invalid protocol entry"), Versions::from_version_string("a,b"));
-        assert_eq!(Err("invalid protocol entry"), Versions::from_version_string("1,!"));
-
-        {
-            let mut versions: HashSet<Version> = HashSet::new();
-            versions.insert(1);
-            assert_eq!(versions, Versions::from_version_string("1").unwrap().0);
-        }
-        {
-            let mut versions: HashSet<Version> = HashSet::new();
-            versions.insert(1);
-            versions.insert(2);
-            assert_eq!(versions, Versions::from_version_string("1,2").unwrap().0);
-        }
-        {
-            let mut versions: HashSet<Version> = HashSet::new();
-            versions.insert(1);
-            versions.insert(2);
-            versions.insert(3);
-            assert_eq!(versions, Versions::from_version_string("1-3").unwrap().0);
-        }
-        {
-            let mut versions: HashSet<Version> = HashSet::new();
-            versions.insert(1);
-            versions.insert(2);
-            versions.insert(5);
-            assert_eq!(versions, Versions::from_version_string("1-2,5").unwrap().0);
-        }
-        {
-            let mut versions: HashSet<Version> = HashSet::new();
-            versions.insert(1);
-            versions.insert(3);
-            versions.insert(4);
-            versions.insert(5);
-            assert_eq!(versions, Versions::from_version_string("1,3-5").unwrap().0);
-        }
-    }
-
     #[test]
     fn test_contains_only_supported_protocols() {
         use super::contains_only_supported_protocols;
@@ -683,91 +835,62 @@ mod test {
     }
 
     #[test]
-    fn test_find_range() {
-        use super::find_range;
+    fn test_compute_vote_simple() {
+        let protos = vec![String::from("Link=3-4"), String::from("Link=3")];
 
-        assert_eq!((false, 0), find_range(&vec![]));
-        assert_eq!((false, 1), find_range(&vec![1]));
-        assert_eq!((true, 2), find_range(&vec![1, 2]));
-        assert_eq!((true, 3), find_range(&vec![1, 2, 3]));
-        assert_eq!((true, 3), find_range(&vec![1, 2, 3, 5]));
+        assert_eq!("Link=3", compute_vote(protos, 2));
     }
 
     #[test]
-    fn test_expand_version_range() {
-        use super::expand_version_range;
-
-        assert_eq!(Err("version string empty"), expand_version_range(""));
-        assert_eq!(Ok(1..3), expand_version_range("1-2"));
-        assert_eq!(Ok(1..5), expand_version_range("1-4"));
-        assert_eq!(
-            Err("cannot parse protocol range lower bound"),
-            expand_version_range("a")
-        );
-        assert_eq!(
-            Err("cannot parse protocol range upper bound"),
-            expand_version_range("1-a")
-        );
-        assert_eq!(Ok(1000..66536), expand_version_range("1000-66535"));
-        assert_eq!(Err("Too many protocols in expanded range"),
-                   expand_version_range("1000-66536"));
+    fn test_compute_vote_merges_ranges() {
+        let protos = vec![
+            String::from("Link=1-3 Relay=1"),
+            String::from("Link=2-4 Relay=1"),
+        ];
+
+        assert_eq!("Link=2-3 Relay=1", compute_vote(protos, 2));
     }
 
     #[test]
-    fn test_contract_protocol_list() {
-        use std::collections::HashSet;
-        use super::contract_protocol_list;
-
-        {
-            let mut versions = HashSet::<Version>::new();
-            assert_eq!(String::from(""), contract_protocol_list(&versions));
-
-            versions.insert(1);
-            assert_eq!(String::from("1"), contract_protocol_list(&versions));
+    fn test_compute_vote_skips_malformed_voters() {
+        let protos = vec![
+            String::from("Link=1"),
+            String::from("definitely not a protocol list"),
+            String::from("Link=1"),
+        ];
+
+        assert_eq!("Link=1", compute_vote(protos, 2));
+    }
 
-            versions.insert(2);
-            assert_eq!(String::from("1-2"), contract_protocol_list(&versions));
-        }
+    #[test]
+    fn test_compute_vote_orders_subprotocols_alphabetically() {
+        let protos = vec![
+            String::from("Relay=1 HSDir=2 Link=3"),
+            String::from("Relay=1 HSDir=2 Link=3"),
+        ];
 
-        {
-            let mut versions = HashSet::<Version>::new();
-            versions.insert(1);
-            versions.insert(3);
-            assert_eq!(String::from("1,3"), contract_protocol_list(&versions));
-        }
+        assert_eq!("HSDir=2 Link=3 Relay=1", compute_vote(protos, 2));
+    }
 
-        {
-            let mut versions = HashSet::<Version>::new();
-            versions.insert(1);
-            versions.insert(2);
-            versions.insert(3);
-            versions.insert(4);
-            assert_eq!(String::from("1-4"), contract_protocol_list(&versions));
-        }
+    #[test]
+    fn test_compute_vote_omits_subprotocols_below_threshold() {
+        let protos = vec![
+            String::from("Link=1-2 Relay=1"),
+            String::from("Link=1 Relay=2"),
+        ];
+
+        // Only Link=1 is listed by both voters; every Relay version and Link=2
+        // fall below the threshold, so Relay is omitted entirely.
+        assert_eq!("Link=1", compute_vote(protos, 2));
+    }
 
-        {
-            let mut versions = HashSet::<Version>::new();
-            versions.insert(1);
-            versions.insert(3);
-            versions.insert(5);
-            versions.insert(6);
-            versions.insert(7);
-            assert_eq!(
-                String::from("1,3,5-7"),
-                contract_protocol_list(&versions)
-            );
-        }
+    #[test]
+    fn test_compute_vote_tallies_unknown_subprotocols() {
+        let protos = vec![
+            String::from("Quux=1-2"),
+            String::from("Quux=2-3"),
+        ];
 
-        {
-            let mut versions = HashSet::<Version>::new();
-            versions.insert(1);
-            versions.insert(2);
-            versions.insert(3);
-            versions.insert(500);
-            assert_eq!(
-                String::from("1-3,500"),
-                contract_protocol_list(&versions)
-            );
-        }
+        assert_eq!("Quux=2", compute_vote(protos, 2));
     }
 }
@@ -47,6 +47,73 @@ pub fn allocate_and_copy_string(src: &String) -> *mut c_char {
     dest as *mut c_char
 }
 
+/// Allocate memory using tor_malloc_ and copy an existing byte slice into the
+/// allocated buffer, returning a pointer that can later be freed in C.
+///
+/// Unlike `allocate_and_copy_string`, this copies exactly `src.len()` bytes with
+/// *no* trailing NUL terminator, so it is safe for binary blobs containing
+/// embedded NUL bytes (e.g. serialized microdescriptor or consensus fragments).
+/// Because there is no terminator, C callers cannot recover the length with
+/// `strlen`; the number of bytes written is returned through `size_out`.
+///
+/// # Inputs
+///
+/// * `src`, a byte slice that will be copied.
+/// * `size_out`, an out-parameter which receives the number of bytes written.
+///   It must not be NULL.
+///
+/// # Returns
+///
+/// A pointer to `src.len()` bytes that should be freed by tor_free in C, or a
+/// NULL pointer if the underlying allocation failed.
+///
+pub fn allocate_and_copy_bytes(src: &[u8], size_out: &mut usize) -> *mut u8 {
+    let size = src.len();
+
+    let dest = unsafe { tor_malloc_(size) as *mut u8 };
+
+    if dest.is_null() {
+        *size_out = 0;
+        return dest;
+    }
+
+    unsafe { ptr::copy_nonoverlapping(src.as_ptr(), dest, size) };
+
+    *size_out = size;
+    dest
+}
+
+/// C-visible entry point for `allocate_and_copy_bytes`.
+///
+/// Copies `len` bytes from `src` into a freshly `tor_malloc_`'d buffer and
+/// returns it, writing the number of bytes copied through `size_out`.  Returns
+/// a NULL pointer (and `*size_out == 0`) if `src` or `size_out` is NULL or the
+/// allocation fails.
+///
+/// C_RUST_COUPLED: src/common/util.c `tor_memdup`
+#[no_mangle]
+pub extern "C" fn allocate_and_copy_bytes_(
+    src: *const u8,
+    len: usize,
+    size_out: *mut usize,
+) -> *mut u8 {
+    if src.is_null() || size_out.is_null() {
+        if !size_out.is_null() {
+            unsafe { *size_out = 0 };
+        }
+        return ptr::null_mut();
+    }
+
+    // Require an unsafe block to build a slice from the C pointer. Both the
+    // pointer and the out-param are checked above to ensure they are not null.
+    let bytes = unsafe { slice::from_raw_parts(src, len) };
+    let mut written: usize = 0;
+    let dest = allocate_and_copy_bytes(bytes, &mut written);
+
+    unsafe { *size_out = written };
+    dest
+}
+
 #[cfg(test)]
 mod test {
 
@@ -87,4 +154,37 @@ mod test {
 
         unsafe { free(allocated_empty as *mut c_void) };
     }
+
+    #[test]
+    fn test_allocate_and_copy_bytes_with_empty() {
+        use libc::{free, c_void};
+
+        use tor_allocate::allocate_and_copy_bytes;
+
+        let mut size: usize = 99;
+        let allocated = allocate_and_copy_bytes(&[], &mut size);
+
+        assert_eq!(0, size);
+
+        unsafe { free(allocated as *mut c_void) };
+    }
+
+    #[test]
+    fn test_allocate_and_copy_bytes_with_embedded_nul() {
+        use std::slice;
+        use libc::{free, c_void};
+
+        use tor_allocate::allocate_and_copy_bytes;
+
+        let bytes: &[u8] = b"foo\0bar\0baz";
+        let mut size: usize = 0;
+        let allocated = allocate_and_copy_bytes(bytes, &mut size);
+
+        assert_eq!(bytes.len(), size);
+
+        let copied = unsafe { slice::from_raw_parts(allocated, size) };
+        assert_eq!(bytes, copied);
+
+        unsafe { free(allocated as *mut c_void) };
+    }
 }
@@ -4,6 +4,8 @@
 //! Utilities for working with static strings.
 
 use std::ffi::CStr;
+use std::ffi::CString;
+use std::ffi::NulError;
 
 /// A byte-array containing a single NUL byte (`b"\0"`).
 pub const NUL_BYTE: &'static [u8] = b"\0";
@@ -191,10 +193,71 @@ macro_rules! cstr {
     )
 }
 
+/// Build a `CString` from a runtime `&str`, deterministically removing any
+/// interior NUL bytes.
+///
+/// Unlike the `cstr!` macro, which only accepts compile-time literals, this may
+/// be called on a *computed* string.  Because a C string cannot contain an
+/// interior NUL byte, any such bytes are stripped from the input before the
+/// `CString` is constructed, so this function never fails.  Use
+/// `try_cstring_from_str` instead if an interior NUL should be treated as an
+/// error rather than silently removed.
+///
+/// This gives the protover FFI one audited path for returning computed strings
+/// to C, rather than each call site re-implementing the
+/// `CString::new(...).unwrap_or(...)` pattern.
+pub fn cstring_from_str_lossy(s: &str) -> CString {
+    let cleaned: Vec<u8> = s.bytes().filter(|&b| b != 0x00).collect();
+
+    // The filtered bytes are guaranteed to contain no interior NUL, so the
+    // `unwrap` cannot fail.
+    CString::new(cleaned).unwrap()
+}
+
+/// Build a `CString` from a runtime `&str`, returning an error if the input
+/// contains an interior NUL byte.
+///
+/// This is the strict companion to `cstring_from_str_lossy`: rather than
+/// silently removing interior NUL bytes it surfaces the `NulError` which
+/// `CString::new` produces, so that callers which must not lose data can refuse
+/// the conversion instead.
+pub fn try_cstring_from_str(s: &str) -> Result<CString, NulError> {
+    CString::new(s)
+}
+
 #[cfg(test)]
 mod test {
     use std::ffi::CStr;
 
+    use super::cstring_from_str_lossy;
+    use super::try_cstring_from_str;
+
+    #[test]
+    fn cstring_from_str_lossy_plain() {
+        let result = cstring_from_str_lossy("Link=1-5 Relay=1-2");
+
+        assert_eq!("Link=1-5 Relay=1-2", result.to_str().unwrap());
+    }
+
+    #[test]
+    fn cstring_from_str_lossy_strips_interior_nul() {
+        let result = cstring_from_str_lossy("foo\0bar\0baz");
+
+        assert_eq!("foobarbaz", result.to_str().unwrap());
+    }
+
+    #[test]
+    fn try_cstring_from_str_plain() {
+        let result = try_cstring_from_str("Link=1-5").unwrap();
+
+        assert_eq!("Link=1-5", result.to_str().unwrap());
+    }
+
+    #[test]
+    fn try_cstring_from_str_rejects_interior_nul() {
+        assert!(try_cstring_from_str("foo\0bar").is_err());
+    }
+
     #[test]
     fn cstr_macro() {
         let _: &'static CStr = cstr!("boo");